@@ -54,7 +54,7 @@ impl Format {
                 writeln!(f, "{}", line.trim())?;
             }
         } else if let Some(line) = lines.map(str::trim).find(|s| !s.is_empty()) {
-            f.write_str(&line)?;
+            f.write_str(line)?;
         }
         Ok(())
     }
@@ -118,7 +118,7 @@ impl Format {
     ) -> fmt::Result {
         match (
             self.current && at == current,
-            self.saved && saved.map_or(false, |saved| saved == at),
+            self.saved && saved == Some(at),
         ) {
             (true, true) => {
                 #[cfg(feature = "colored")]
@@ -1,7 +1,7 @@
 //! A history of actions.
 
 use crate::record::Builder as RBuilder;
-use crate::{Action, At, Entry, Format, Record, Result, Signal};
+use crate::{Action, At, Entry, Format, Merged, Record, Result, Signal};
 use alloc::{
     boxed::Box,
     collections::{BTreeMap, VecDeque},
@@ -20,6 +20,9 @@ use serde_crate::{Deserialize, Serialize};
 /// Unlike [Record](struct.Record.html) which maintains a linear undo history, History maintains an undo tree
 /// containing every edit made to the target.
 ///
+/// A history can be converted into a record with `Record::from`. The record keeps only the
+/// actions along the current branch, from the root to its tip, and every other branch is discarded.
+///
 /// # Examples
 /// ```
 /// # use undo::{Action, History};
@@ -148,39 +151,215 @@ impl<A, F> History<A, F> {
         self.record.current()
     }
 
+    /// Returns the number of actions in `branch`, counted from the root of the history.
+    ///
+    /// Returns `None` if `branch` does not exist.
+    pub fn branch_len(&self, branch: usize) -> Option<usize> {
+        if branch == self.branch() {
+            Some(self.len())
+        } else {
+            self.branches
+                .get(&branch)
+                .map(|branch| branch.parent.current + branch.entries.len())
+        }
+    }
+
+    /// Returns an iterator over the branches in the history, ordered by their id,
+    /// together with the position each branch diverges from.
+    ///
+    /// The tree is always rooted in the current branch, which is the only branch
+    /// without a parent. The parents of the other branches change as the current branch changes.
+    pub fn branches(&self) -> impl Iterator<Item = (usize, Option<At>)> + '_ {
+        let root = self.branch();
+        let parent = |(&id, branch): (&usize, &Branch<A>)| (id, Some(branch.parent));
+        self.branches
+            .range(..root)
+            .map(parent)
+            .chain(core::iter::once((root, None)))
+            .chain(self.branches.range(root + 1..).map(parent))
+    }
+
+    /// Returns the action which will be undone in the next call to
+    /// [`undo`](struct.History.html#method.undo).
+    pub fn peek_undo(&self) -> Option<&A> {
+        self.record.peek_undo()
+    }
+
+    /// Returns the action which will be redone in the next call to
+    /// [`redo`](struct.History.html#method.redo).
+    pub fn peek_redo(&self) -> Option<&A> {
+        self.record.peek_redo()
+    }
+
+    /// Returns an iterator over the actions in the current branch, from the oldest to the newest.
+    ///
+    /// The action at index `i` is currently applied to the target if `i` is less than
+    /// [`current`](struct.History.html#method.current), and undone otherwise.
+    pub fn actions(&self) -> impl Iterator<Item = &A> {
+        self.record.actions()
+    }
+
+    /// Returns the position right after the first action that matches the predicate.
+    ///
+    /// The current branch is searched first, from the oldest to the newest action,
+    /// followed by the other branches ordered by their id. Only the actions that
+    /// belong to a branch itself are searched, not the ones it shares with its parent.
+    /// Passing the position to [`go_to`](struct.History.html#method.go_to)
+    /// moves the target to the state where that action was the last one applied.
+    pub fn find(&self, mut f: impl FnMut(&A) -> bool) -> Option<At> {
+        if let Some(current) = self.record.find(&mut f) {
+            return Some(At::new(self.branch(), current));
+        }
+        self.branches.iter().find_map(|(&id, branch)| {
+            branch
+                .entries
+                .iter()
+                .position(|e| f(&e.action))
+                .map(|i| At::new(id, branch.parent.current + i + 1))
+        })
+    }
+
+    /// Returns the number of undo and redo calls, in that order, that
+    /// [`go_to`](struct.History.html#method.go_to) would make to reach `current` in `branch`.
+    ///
+    /// Actions that are applied when walking into other branches are counted as redo calls.
+    /// Returns `None` if the position does not exist.
+    pub fn plan_go_to(&self, branch: usize, current: usize) -> Option<(usize, usize)> {
+        if branch == self.branch() {
+            return self.record.plan_go_to(current);
+        }
+        // Walk the path from `branch` back to `root`.
+        let mut path = Vec::new();
+        let mut i = branch;
+        while i != self.branch() {
+            let branch = self.branches.get(&i)?;
+            i = branch.parent.branch;
+            path.push(branch);
+        }
+        let (mut undos, mut redos) = (0, 0);
+        let mut at = self.current();
+        for branch in path.into_iter().rev() {
+            let parent = branch.parent.current;
+            if parent < at {
+                undos += at - parent;
+            } else {
+                redos += parent - at;
+            }
+            redos += branch.entries.len();
+            at = parent + branch.entries.len();
+        }
+        if current > at {
+            None
+        } else {
+            Some((undos + at - current, redos))
+        }
+    }
+
     /// Returns a queue.
-    pub fn queue(&mut self) -> Queue<A, F> {
+    pub fn queue(&mut self) -> Queue<'_, A, F> {
         Queue::from(self)
     }
 
     /// Returns a checkpoint.
-    pub fn checkpoint(&mut self) -> Checkpoint<A, F> {
+    pub fn checkpoint(&mut self) -> Checkpoint<'_, A, F> {
         Checkpoint::from(self)
     }
 
     /// Returns a structure for configurable formatting of the history.
-    pub fn display(&self) -> Display<A, F> {
+    pub fn display(&self) -> Display<'_, A, F> {
         Display::from(self)
     }
 
-    fn at(&self) -> At {
+    /// Returns the position of the current action in the history tree.
+    pub fn at(&self) -> At {
         At::new(self.branch(), self.current())
     }
+
+    /// Returns the position marked as the saved state, or `None` if there is no saved state.
+    ///
+    /// The saved state can be in another branch than the current one.
+    pub fn saved(&self) -> Option<At> {
+        self.record
+            .saved()
+            .map(|saved| At::new(self.branch(), saved))
+            .or(self.saved)
+    }
 }
 
 impl<A: Action, F: FnMut(Signal)> History<A, F> {
     /// Pushes the action to the top of the history and executes its [`apply`] method.
     ///
+    /// If there are actions to redo, they are moved into a new branch and the action
+    /// is not merged, since the previous action is shared with that branch.
+    ///
     /// # Errors
     /// If an error occur when executing [`apply`] the error is returned.
     ///
     /// [`apply`]: trait.Action.html#tymethod.apply
     pub fn apply(&mut self, target: &mut A::Target, action: A) -> Result<A> {
+        self.__apply(target, action, true).map(|(output, _)| output)
+    }
+
+    /// Pushes the action to the top of the history and executes its [`apply`] method,
+    /// without trying to merge it with the previous action.
+    ///
+    /// See [`Record::apply_no_merge`](struct.Record.html#method.apply_no_merge).
+    ///
+    /// # Errors
+    /// If an error occur when executing [`apply`] the error is returned.
+    ///
+    /// [`apply`]: trait.Action.html#tymethod.apply
+    pub fn apply_no_merge(&mut self, target: &mut A::Target, action: A) -> Result<A> {
+        self.__apply(target, action, false)
+            .map(|(output, _)| output)
+    }
+
+    /// Pushes the applied actions of `record` on top of the history without applying them,
+    /// and returns the branch they were pushed to.
+    ///
+    /// The actions must have been applied to the target starting from the state it was in at
+    /// the current position of the history, otherwise undo and redo will misbehave.
+    /// Just like in [`apply`](struct.History.html#method.apply) the actions to redo are moved
+    /// into a new branch, and the history ends up at the last pushed action. The actions are
+    /// never merged. The undone actions of `record` are dropped. The saved state of `record`
+    /// is ignored, so the history keeps its own saved state.
+    ///
+    /// Returns `None`, and leaves the history untouched, if `record` has no applied actions.
+    pub fn extend_from_record<G>(&mut self, record: Record<A, G>) -> Option<usize> {
+        let current = record.current();
+        if current == 0 {
+            return None;
+        }
+        let mut entries = record.entries;
+        entries.truncate(current);
+        for entry in entries {
+            self.__push(entry, false);
+        }
+        Some(self.branch())
+    }
+
+    fn __apply(
+        &mut self,
+        target: &mut A::Target,
+        mut action: A,
+        merge: bool,
+    ) -> core::result::Result<(A::Output, Merged), A::Error> {
+        let output = action.apply(target)?;
+        let merged = self.__push(Entry::from(action), merge);
+        Ok((output, merged))
+    }
+
+    fn __push(&mut self, entry: Entry<A>, merge: bool) -> Merged {
         let at = self.at();
         let saved = self.record.saved.filter(|&saved| saved > at.current);
-        let (output, merged, tail) = self.record.__apply(target, action)?;
+        // The action below a redo branch, or below any branch that diverges at `at`, is
+        // shared with that branch, so it can not be changed by a merge or annulled.
+        // This also keeps the branches splitting at `at.current`.
+        let merge =
+            merge && !self.can_redo() && !self.branches.values().any(|branch| branch.parent == at);
+        let (merged, tail) = self.record.__push(entry, merge);
         // Check if the limit has been reached.
-        if !merged && at.current == self.current() {
+        if merged == Merged::No && at.current == self.current() {
             let root = self.branch();
             self.rm_child(root, 0);
             self.branches
@@ -196,12 +375,15 @@ impl<A: Action, F: FnMut(Signal)> History<A, F> {
                 .insert(at.branch, Branch::new(new, at.current, tail));
             self.set_root(new, at.current, saved);
         }
-        Ok(output)
+        merged
     }
 
     /// Calls the [`undo`] method for the active action
     /// and sets the previous one as the new active one.
     ///
+    /// This never changes the current branch, so on success the new position
+    /// is [`at`](struct.History.html#method.at) with `current` decremented by one.
+    ///
     /// # Errors
     /// If an error occur when executing [`undo`] the error is returned.
     ///
@@ -213,6 +395,9 @@ impl<A: Action, F: FnMut(Signal)> History<A, F> {
     /// Calls the [`redo`] method for the active action
     /// and sets the next one as the new active one.
     ///
+    /// This never changes the current branch, so on success the new position
+    /// is [`at`](struct.History.html#method.at) with `current` incremented by one.
+    ///
     /// # Errors
     /// If an error occur when executing [`redo`] the error is returned.
     ///
@@ -236,6 +421,71 @@ impl<A: Action, F: FnMut(Signal)> History<A, F> {
         self.branches.clear();
     }
 
+    /// Sets the `limit` of the history.
+    ///
+    /// The current branch is shortened like [`Record::set_limit`]. The branches that diverge
+    /// from one of the removed actions are removed, and the other branches are cut to the limit.
+    ///
+    /// # Panics
+    /// Panics if `limit` is `0`.
+    ///
+    /// [`Record::set_limit`]: struct.Record.html#method.set_limit
+    pub fn set_limit(&mut self, limit: usize) {
+        let root = self.branch();
+        let evict = self.len().saturating_sub(limit).min(self.current());
+        self.record.set_limit(limit);
+        // Remove the branches below the evicted actions.
+        (0..evict).for_each(|current| self.rm_child(root, current));
+        self.branches
+            .values_mut()
+            .for_each(|branch| branch.parent.current -= evict);
+        self.saved = self
+            .saved
+            .and_then(|saved| Some(At::new(saved.branch, saved.current.checked_sub(evict)?)));
+        // Remove the branches that have no room left, and cut the rest.
+        self.branches
+            .retain(|_, branch| branch.parent.current < limit);
+        self.branches
+            .values_mut()
+            .for_each(|branch| branch.entries.truncate(limit - branch.parent.current));
+        let branches = &self.branches;
+        self.saved = self
+            .saved
+            .filter(|saved| saved.current <= limit && branches.contains_key(&saved.branch));
+    }
+
+    /// Removes the branches where every action is older than `cutoff`,
+    /// and returns the number of branches removed.
+    ///
+    /// A branch is only removed once all of its child branches have been removed,
+    /// and the current branch is never removed.
+    #[cfg(feature = "chrono")]
+    pub fn prune_before(&mut self, cutoff: &DateTime<Utc>) -> usize {
+        let mut removed = 0;
+        loop {
+            let dead: Vec<_> = self
+                .branches
+                .iter()
+                .filter(|(_, branch)| branch.entries.iter().all(|e| e.timestamp < *cutoff))
+                .filter(|&(&id, _)| {
+                    !self
+                        .branches
+                        .values()
+                        .any(|child| child.parent.branch == id)
+                })
+                .map(|(&id, _)| id)
+                .collect();
+            if dead.is_empty() {
+                return removed;
+            }
+            for id in dead {
+                self.branches.remove(&id);
+                self.saved = self.saved.filter(|saved| saved.branch != id);
+                removed += 1;
+            }
+        }
+    }
+
     pub(crate) fn jump_to(&mut self, root: usize) {
         let mut branch = self.branches.remove(&root).unwrap();
         debug_assert_eq!(branch.parent, self.at());
@@ -324,8 +574,13 @@ impl<A: Action, F: FnMut(Signal)> History<A, F> {
 impl<A: Action<Output = ()>, F: FnMut(Signal)> History<A, F> {
     /// Repeatedly calls [`undo`] or [`redo`] until the action in `branch` at `current` is reached.
     ///
+    /// Returns `None` if `branch` does not exist or `current` is out of bounds in it.
+    /// The history and the target are then left untouched.
+    /// Returns `Some(Ok(()))` if the position was reached.
+    ///
     /// # Errors
-    /// If an error occur when executing [`undo`] or [`redo`] the error is returned.
+    /// If an error occur when executing [`undo`] or [`redo`] the error is returned as `Some(Err(_))`,
+    /// and the history is left at the position where the error occurred.
     ///
     /// [`undo`]: trait.Action.html#tymethod.undo
     /// [`redo`]: trait.Action.html#method.redo
@@ -335,6 +590,8 @@ impl<A: Action<Output = ()>, F: FnMut(Signal)> History<A, F> {
         branch: usize,
         current: usize,
     ) -> Option<Result<A>> {
+        // Check that the position exists before changing anything.
+        self.plan_go_to(branch, current)?;
         let root = self.root;
         if root == branch {
             return self.record.go_to(target, current);
@@ -345,11 +602,12 @@ impl<A: Action<Output = ()>, F: FnMut(Signal)> History<A, F> {
             if let Err(err) = self.record.go_to(target, branch.parent.current).unwrap() {
                 return Some(Err(err));
             }
-            // Apply the actions in the branch and move older actions into their own branch.
+            // Apply the actions in the branch without merging them, and move older actions
+            // into their own branch.
             for entry in branch.entries {
                 let current = self.current();
                 let saved = self.record.saved.filter(|&saved| saved > current);
-                let entries = match self.record.__apply(target, entry.action) {
+                let entries = match self.record.__apply(target, entry.action, false) {
                     Ok((_, _, entries)) => entries,
                     Err(err) => return Some(Err(err)),
                 };
@@ -363,6 +621,28 @@ impl<A: Action<Output = ()>, F: FnMut(Signal)> History<A, F> {
         self.record.go_to(target, current)
     }
 
+    /// Calls [`undo`] until the start of the current branch is reached.
+    ///
+    /// # Errors
+    /// If an error occur when executing [`undo`] the error is returned
+    /// and the history is left at the position of the failing action.
+    ///
+    /// [`undo`]: trait.Action.html#tymethod.undo
+    pub fn undo_all(&mut self, target: &mut A::Target) -> Result<A> {
+        self.record.undo_all(target)
+    }
+
+    /// Calls [`redo`] until the end of the current branch is reached.
+    ///
+    /// # Errors
+    /// If an error occur when executing [`redo`] the error is returned
+    /// and the history is left at the position of the failing action.
+    ///
+    /// [`redo`]: trait.Action.html#method.redo
+    pub fn redo_all(&mut self, target: &mut A::Target) -> Result<A> {
+        self.record.redo_all(target)
+    }
+
     /// Go back or forward in the history to the action that was made closest to the datetime provided.
     ///
     /// This method does not jump across branches.
@@ -472,6 +752,9 @@ impl<F> Builder<F> {
     }
 
     /// Sets the capacity for the history.
+    ///
+    /// See [`record::Builder::capacity`](../record/struct.Builder.html#method.capacity)
+    /// for how this interacts with the limit.
     pub fn capacity(self, capacity: usize) -> Builder<F> {
         Builder(self.0.capacity(capacity))
     }
@@ -593,12 +876,12 @@ impl<A: Action<Output = ()>, F: FnMut(Signal)> Queue<'_, A, F> {
     pub fn cancel(self) {}
 
     /// Returns a queue.
-    pub fn queue(&mut self) -> Queue<A, F> {
+    pub fn queue(&mut self) -> Queue<'_, A, F> {
         self.history.queue()
     }
 
     /// Returns a checkpoint.
-    pub fn checkpoint(&mut self) -> Checkpoint<A, F> {
+    pub fn checkpoint(&mut self) -> Checkpoint<'_, A, F> {
         self.history.checkpoint()
     }
 }
@@ -612,26 +895,51 @@ impl<'a, A, F> From<&'a mut History<A, F>> for Queue<'a, A, F> {
     }
 }
 
+impl<A, F> Extend<A> for Queue<'_, A, F> {
+    /// Queues an `apply` action for each action in the iterator.
+    ///
+    /// The actions are applied after any actions that are already queued, in the order of the iterator.
+    fn extend<I: IntoIterator<Item = A>>(&mut self, actions: I) {
+        self.actions
+            .extend(actions.into_iter().map(QueueAction::Apply));
+    }
+}
+
 #[derive(Debug)]
 enum CheckpointAction {
-    Apply(usize),
+    Apply(usize, Merged),
     Undo,
     Redo,
 }
 
 /// Wraps a history and gives it checkpoint functionality.
+///
+/// Checkpoints can be nested by calling [`checkpoint`](struct.Checkpoint.html#method.checkpoint).
+/// Canceling a nested checkpoint only cancels its own changes. When a nested checkpoint is
+/// committed its changes become part of the outer checkpoint,
+/// so canceling the outer checkpoint cancels them too.
 #[derive(Debug)]
 pub struct Checkpoint<'a, A, F> {
     history: &'a mut History<A, F>,
     actions: Vec<CheckpointAction>,
+    parent: Option<&'a mut Vec<CheckpointAction>>,
 }
 
 impl<A: Action<Output = ()>, F: FnMut(Signal)> Checkpoint<'_, A, F> {
     /// Calls the `apply` method.
+    ///
+    /// The action is only merged with the previous action if that was also applied
+    /// by this checkpoint, so canceling never has to split up an action from before it.
+    /// The first action of a checkpoint is therefore never merged with the action that
+    /// was on top before the checkpoint was created.
     pub fn apply(&mut self, target: &mut A::Target, action: A) -> Result<A> {
         let branch = self.history.branch();
-        self.history.apply(target, action)?;
-        self.actions.push(CheckpointAction::Apply(branch));
+        let merge = matches!(
+            self.actions.last(),
+            Some(CheckpointAction::Apply(_, Merged::Yes | Merged::No))
+        );
+        let (_, merged) = self.history.__apply(target, action, merge)?;
+        self.actions.push(CheckpointAction::Apply(branch, merged));
         Ok(())
     }
 
@@ -657,23 +965,53 @@ impl<A: Action<Output = ()>, F: FnMut(Signal)> Checkpoint<'_, A, F> {
         }
     }
 
+    /// Returns the number of actions in the checkpoint.
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    /// Returns `true` if the checkpoint is empty.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
     /// Commits the changes and consumes the checkpoint.
-    pub fn commit(self) {}
+    ///
+    /// If the checkpoint is nested, its changes are handed to the outer checkpoint.
+    pub fn commit(mut self) {
+        if let Some(parent) = self.parent {
+            parent.append(&mut self.actions);
+        }
+    }
 
     /// Cancels the changes and consumes the checkpoint.
     ///
     /// # Errors
     /// If an error occur when canceling the changes, the error is returned
     /// and the remaining actions are not canceled.
-    pub fn cancel(self, target: &mut A::Target) -> Option<Result<A>> {
-        for action in self.actions.into_iter().rev() {
+    pub fn cancel(mut self, target: &mut A::Target) -> Option<Result<A>> {
+        // The number of pushed actions that have since been annulled.
+        let mut annulled = 0;
+        while let Some(action) = self.actions.pop() {
             match action {
-                CheckpointAction::Apply(branch) => {
-                    let root = self.history.branch();
-                    self.history.jump_to(branch);
-                    if root == branch {
-                        self.history.record.entries.pop_back();
+                // A merged action is undone together with the action it was merged into.
+                CheckpointAction::Apply(_, Merged::Yes) => (),
+                CheckpointAction::Apply(_, Merged::Annul) => annulled += 1,
+                CheckpointAction::Apply(branch, Merged::No) => {
+                    if annulled > 0 {
+                        annulled -= 1;
                     } else {
+                        match self.history.undo(target) {
+                            Some(Ok(())) => {
+                                self.history.record.entries.pop_back();
+                            }
+                            o => return o,
+                        }
+                    }
+                    // Move back to the branch the action was applied in, if it created a new one.
+                    let root = self.history.branch();
+                    if root != branch {
+                        self.history.jump_to(branch);
                         self.history.branches.remove(&root).unwrap();
                     }
                 }
@@ -691,13 +1029,17 @@ impl<A: Action<Output = ()>, F: FnMut(Signal)> Checkpoint<'_, A, F> {
     }
 
     /// Returns a queue.
-    pub fn queue(&mut self) -> Queue<A, F> {
+    pub fn queue(&mut self) -> Queue<'_, A, F> {
         self.history.queue()
     }
 
     /// Returns a checkpoint.
-    pub fn checkpoint(&mut self) -> Checkpoint<A, F> {
-        self.history.checkpoint()
+    pub fn checkpoint(&mut self) -> Checkpoint<'_, A, F> {
+        Checkpoint {
+            history: self.history,
+            actions: Vec::new(),
+            parent: Some(&mut self.actions),
+        }
     }
 }
 
@@ -706,6 +1048,7 @@ impl<'a, A, F> From<&'a mut History<A, F>> for Checkpoint<'a, A, F> {
         Checkpoint {
             history,
             actions: Vec::new(),
+            parent: None,
         }
     }
 }
@@ -769,16 +1112,8 @@ impl<A: fmt::Display, F> Display<'_, A, F> {
             }
         }
 
-        self.format.labels(
-            f,
-            at,
-            At::new(self.history.branch(), self.history.current()),
-            self.history
-                .record
-                .saved
-                .map(|saved| At::new(self.history.branch(), saved))
-                .or(self.history.saved),
-        )?;
+        self.format
+            .labels(f, at, self.history.at(), self.history.saved())?;
         if let Some(entry) = entry {
             if self.format.detailed {
                 writeln!(f)?;
@@ -847,7 +1182,7 @@ impl<A: fmt::Display, F> fmt::Display for Display<'_, A, F> {
 #[cfg(test)]
 mod tests {
     use crate::*;
-    use alloc::string::String;
+    use alloc::{string::String, vec::Vec};
 
     struct Add(char);
 
@@ -867,6 +1202,33 @@ mod tests {
         }
     }
 
+    struct Step(i32);
+
+    impl Action for Step {
+        type Target = i32;
+        type Output = ();
+        type Error = ();
+
+        fn apply(&mut self, n: &mut i32) -> Result<Step> {
+            *n += self.0;
+            Ok(())
+        }
+
+        fn undo(&mut self, n: &mut i32) -> Result<Step> {
+            *n -= self.0;
+            Ok(())
+        }
+
+        fn merge(&mut self, step: &mut Self) -> Merged {
+            self.0 += step.0;
+            if self.0 == 0 {
+                Merged::Annul
+            } else {
+                Merged::Yes
+            }
+        }
+    }
+
     #[test]
     fn go_to() {
         //          m
@@ -942,4 +1304,362 @@ mod tests {
         history.go_to(&mut target, abnpq, 5).unwrap().unwrap();
         assert_eq!(target, "abnpq");
     }
+
+    #[test]
+    fn branches() {
+        let mut target = String::new();
+        let mut history = History::new();
+        history.apply(&mut target, Add('a')).unwrap();
+        history.apply(&mut target, Add('b')).unwrap();
+        history.undo(&mut target).unwrap().unwrap();
+        let ab = history.branch();
+        history.apply(&mut target, Add('c')).unwrap();
+        let ac = history.branch();
+        let branches: Vec<_> = history.branches().collect();
+        assert_eq!(branches, [(ab, Some(At::new(ac, 1))), (ac, None)]);
+        history.go_to(&mut target, ab, 2).unwrap().unwrap();
+        let branches: Vec<_> = history.branches().collect();
+        assert_eq!(branches, [(ab, None), (ac, Some(At::new(ab, 1)))]);
+    }
+
+    #[test]
+    fn plan_go_to() {
+        let mut target = String::new();
+        let mut history = History::new();
+        history.apply(&mut target, Add('a')).unwrap();
+        history.apply(&mut target, Add('b')).unwrap();
+        history.undo(&mut target).unwrap().unwrap();
+        let ab = history.branch();
+        history.apply(&mut target, Add('c')).unwrap();
+        history.apply(&mut target, Add('d')).unwrap();
+        let acd = history.branch();
+        assert_eq!(history.plan_go_to(acd, 1), Some((2, 0)));
+        assert_eq!(history.plan_go_to(acd, 4), None);
+        assert_eq!(history.plan_go_to(ab, 2), Some((2, 1)));
+        assert_eq!(history.plan_go_to(ab, 3), None);
+        assert_eq!(history.plan_go_to(100, 0), None);
+        history.go_to(&mut target, ab, 2).unwrap().unwrap();
+        assert_eq!(history.plan_go_to(acd, 3), Some((1, 2)));
+    }
+
+    #[test]
+    fn branch_len() {
+        let mut target = String::new();
+        let mut history = History::new();
+        history.apply(&mut target, Add('a')).unwrap();
+        history.apply(&mut target, Add('b')).unwrap();
+        history.undo(&mut target).unwrap().unwrap();
+        let ab = history.branch();
+        history.apply(&mut target, Add('c')).unwrap();
+        history.apply(&mut target, Add('d')).unwrap();
+        let acd = history.branch();
+        assert_eq!(history.branch_len(ab), Some(2));
+        assert_eq!(history.branch_len(acd), Some(3));
+        assert_eq!(history.branch_len(100), None);
+    }
+
+    #[test]
+    fn set_limit() {
+        let mut target = String::new();
+        let mut history = History::new();
+        history.apply(&mut target, Add('a')).unwrap();
+        history.undo(&mut target).unwrap().unwrap();
+        let a = history.branch();
+        history.apply(&mut target, Add('b')).unwrap();
+        history.apply(&mut target, Add('c')).unwrap();
+        history.apply(&mut target, Add('d')).unwrap();
+        let bcd = history.branch();
+        history.go_to(&mut target, bcd, 1).unwrap().unwrap();
+        history.apply(&mut target, Add('e')).unwrap();
+        history.apply(&mut target, Add('f')).unwrap();
+        history.apply(&mut target, Add('g')).unwrap();
+        let befg = history.branch();
+        assert_eq!(target, "befg");
+        assert_eq!(history.branches().count(), 3);
+        // `b` is evicted, so the branch that diverges before it is removed.
+        history.set_limit(3);
+        assert_eq!(history.limit(), 3);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.current(), 3);
+        assert_eq!(history.branches().count(), 2);
+        assert_eq!(history.branch_len(a), None);
+        assert_eq!(history.branch_len(bcd), Some(2));
+        history.go_to(&mut target, bcd, 2).unwrap().unwrap();
+        assert_eq!(target, "bcd");
+        // The other branch is cut to the limit.
+        history.set_limit(2);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.branch_len(befg), Some(2));
+        history.go_to(&mut target, befg, 2).unwrap().unwrap();
+        assert_eq!(target, "bef");
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn peek() {
+        let mut target = String::new();
+        let mut history = History::new();
+        assert!(history.peek_undo().is_none());
+        assert!(history.peek_redo().is_none());
+        history.apply(&mut target, Add('a')).unwrap();
+        history.apply(&mut target, Add('b')).unwrap();
+        assert_eq!(history.peek_undo().unwrap().0, 'b');
+        assert!(history.peek_redo().is_none());
+        history.undo(&mut target).unwrap().unwrap();
+        assert_eq!(history.peek_undo().unwrap().0, 'a');
+        assert_eq!(history.peek_redo().unwrap().0, 'b');
+        history.apply(&mut target, Add('c')).unwrap();
+        assert_eq!(history.peek_undo().unwrap().0, 'c');
+        assert!(history.peek_redo().is_none());
+    }
+
+    #[test]
+    fn find() {
+        let mut target = String::new();
+        let mut history = History::new();
+        history.apply(&mut target, Add('a')).unwrap();
+        history.apply(&mut target, Add('b')).unwrap();
+        history.undo(&mut target).unwrap().unwrap();
+        let ab = history.branch();
+        history.apply(&mut target, Add('c')).unwrap();
+        history.apply(&mut target, Add('b')).unwrap();
+        let acb = history.branch();
+        assert_eq!(history.find(|a| a.0 == 'a'), Some(At::new(acb, 1)));
+        assert_eq!(history.find(|a| a.0 == 'b'), Some(At::new(acb, 3)));
+        history.undo(&mut target).unwrap().unwrap();
+        history.undo(&mut target).unwrap().unwrap();
+        history.apply(&mut target, Add('d')).unwrap();
+        assert_eq!(history.find(|a| a.0 == 'b'), Some(At::new(ab, 2)));
+        assert_eq!(history.find(|a| a.0 == 'z'), None);
+        let at = history.find(|a| a.0 == 'c').unwrap();
+        history
+            .go_to(&mut target, at.branch(), at.current())
+            .unwrap()
+            .unwrap();
+        assert_eq!(target, "ac");
+    }
+
+    #[test]
+    fn go_to_invalid() {
+        let mut target = String::new();
+        let mut history = History::new();
+        history.apply(&mut target, Add('a')).unwrap();
+        history.apply(&mut target, Add('b')).unwrap();
+        history.undo(&mut target).unwrap().unwrap();
+        let ab = history.branch();
+        history.apply(&mut target, Add('c')).unwrap();
+        let at = history.at();
+        assert!(history.go_to(&mut target, ab, 3).is_none());
+        assert!(history.go_to(&mut target, 100, 0).is_none());
+        assert_eq!(history.at(), at);
+        assert_eq!(target, "ac");
+    }
+
+    #[test]
+    fn checkpoint_nested() {
+        let mut target = String::new();
+        let mut history = History::new();
+        history.apply(&mut target, Add('a')).unwrap();
+        history.apply(&mut target, Add('b')).unwrap();
+        history.apply(&mut target, Add('c')).unwrap();
+        let mut outer = history.checkpoint();
+        outer.undo(&mut target).unwrap().unwrap();
+        let mut inner = outer.checkpoint();
+        inner.undo(&mut target).unwrap().unwrap();
+        inner.cancel(&mut target).unwrap().unwrap();
+        assert_eq!(target, "ab");
+        let mut inner = outer.checkpoint();
+        inner.undo(&mut target).unwrap().unwrap();
+        inner.commit();
+        assert_eq!(target, "a");
+        outer.cancel(&mut target).unwrap().unwrap();
+        assert_eq!(target, "abc");
+
+        history.undo(&mut target).unwrap().unwrap();
+        let abc = history.branch();
+        let mut outer = history.checkpoint();
+        outer.apply(&mut target, Add('d')).unwrap();
+        let mut inner = outer.checkpoint();
+        inner.apply(&mut target, Add('e')).unwrap();
+        inner.cancel(&mut target).unwrap().unwrap();
+        assert_eq!(target, "abd");
+        let mut inner = outer.checkpoint();
+        inner.apply(&mut target, Add('f')).unwrap();
+        inner.commit();
+        assert_eq!(target, "abdf");
+        outer.cancel(&mut target).unwrap().unwrap();
+        assert_eq!(target, "ab");
+        assert_eq!(history.branch(), abc);
+        assert_eq!(history.branches().count(), 1);
+        history.redo(&mut target).unwrap().unwrap();
+        assert_eq!(target, "abc");
+    }
+
+    #[test]
+    fn checkpoint_annul() {
+        let mut target = 0;
+        let mut history = History::new();
+        history.apply_no_merge(&mut target, Step(1)).unwrap();
+        history.apply_no_merge(&mut target, Step(2)).unwrap();
+        let mut cp = history.checkpoint();
+        cp.apply(&mut target, Step(-2)).unwrap();
+        cp.apply(&mut target, Step(5)).unwrap();
+        cp.apply(&mut target, Step(-3)).unwrap();
+        cp.apply(&mut target, Step(4)).unwrap();
+        assert_eq!(target, 7);
+        cp.cancel(&mut target).unwrap().unwrap();
+        assert_eq!(target, 3);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.current(), 2);
+    }
+
+    #[test]
+    fn annul() {
+        let mut target = 0;
+        let mut history = History::new();
+        history.apply_no_merge(&mut target, Step(1)).unwrap();
+        history.apply_no_merge(&mut target, Step(2)).unwrap();
+        history.apply_no_merge(&mut target, Step(4)).unwrap();
+        history.undo(&mut target).unwrap().unwrap();
+        let old = history.branch();
+        history.apply(&mut target, Step(-2)).unwrap();
+        let new = history.branch();
+        assert_eq!(target, 1);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.branch_len(old), Some(3));
+        assert_eq!(history.plan_go_to(old, 3), Some((1, 1)));
+        history.go_to(&mut target, old, 3).unwrap().unwrap();
+        assert_eq!(target, 7);
+        history.go_to(&mut target, new, 3).unwrap().unwrap();
+        assert_eq!(target, 1);
+
+        history.apply(&mut target, Step(2)).unwrap();
+        assert_eq!(target, 3);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.current(), 2);
+        history.undo(&mut target).unwrap().unwrap();
+        assert_eq!(target, 1);
+    }
+
+    #[test]
+    fn annul_shared() {
+        let mut target = 0;
+        let mut history = History::new();
+        history.apply_no_merge(&mut target, Step(1)).unwrap();
+        history.apply_no_merge(&mut target, Step(2)).unwrap();
+        let old = history.branch();
+        history.undo(&mut target).unwrap().unwrap();
+        history.apply_no_merge(&mut target, Step(3)).unwrap();
+        history.apply(&mut target, Step(-3)).unwrap();
+        assert_eq!(history.branches().next(), Some((old, Some(At::new(1, 1)))));
+        // The branch below shares the top action, so it must not be annulled.
+        history.apply(&mut target, Step(-1)).unwrap();
+        assert_eq!(target, 0);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.plan_go_to(old, 2), Some((1, 1)));
+        history.go_to(&mut target, old, 2).unwrap().unwrap();
+        assert_eq!(target, 3);
+    }
+
+    #[test]
+    fn merge_shared() {
+        let mut target = 0;
+        let mut history = History::new();
+        history.apply_no_merge(&mut target, Step(1)).unwrap();
+        history.apply_no_merge(&mut target, Step(2)).unwrap();
+        let old = history.branch();
+        history.undo(&mut target).unwrap().unwrap();
+        history.apply_no_merge(&mut target, Step(3)).unwrap();
+        history.apply(&mut target, Step(-3)).unwrap();
+        // The branch below shares the top action, so it must not be changed by a merge.
+        history.apply(&mut target, Step(10)).unwrap();
+        assert_eq!(target, 11);
+        assert_eq!(history.len(), 2);
+        history.go_to(&mut target, old, 2).unwrap().unwrap();
+        assert_eq!(target, 3);
+    }
+
+    #[test]
+    fn apply_no_merge() {
+        let mut target = 0;
+        let mut history = History::new();
+        history.apply(&mut target, Step(1)).unwrap();
+        history.apply(&mut target, Step(2)).unwrap();
+        history.apply_no_merge(&mut target, Step(3)).unwrap();
+        assert_eq!(history.len(), 2);
+        history.undo(&mut target).unwrap().unwrap();
+        assert_eq!(target, 3);
+    }
+
+    #[test]
+    fn extend_from_record() {
+        let mut target = String::new();
+        let mut history = History::new();
+        history.apply(&mut target, Add('a')).unwrap();
+        history.apply(&mut target, Add('b')).unwrap();
+        history.undo(&mut target).unwrap().unwrap();
+        let ab = history.branch();
+        let mut record = Record::new();
+        record.apply(&mut target, Add('c')).unwrap();
+        record.apply(&mut target, Add('d')).unwrap();
+        record.apply(&mut target, Add('e')).unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        let acd = history.extend_from_record(record).unwrap();
+        assert_ne!(acd, ab);
+        assert!(history.extend_from_record(Record::new()).is_none());
+        assert_eq!(target, "acd");
+        assert_eq!(history.at(), At::new(acd, 3));
+        assert_eq!(history.branch_len(acd), Some(3));
+        history.go_to(&mut target, ab, 2).unwrap().unwrap();
+        assert_eq!(target, "ab");
+        history.go_to(&mut target, acd, 2).unwrap().unwrap();
+        assert_eq!(target, "ac");
+    }
+
+    #[test]
+    fn extend_from_record_saved() {
+        let mut target = String::new();
+        let mut history = History::new();
+        history.apply(&mut target, Add('a')).unwrap();
+        history.set_saved(true);
+        history.apply(&mut target, Add('b')).unwrap();
+        let mut record = Record::new();
+        record.apply(&mut target, Add('c')).unwrap();
+        history.extend_from_record(record).unwrap();
+        assert_eq!(history.saved(), Some(At::new(0, 1)));
+        assert!(!history.is_saved());
+        history.go_to(&mut target, 0, 1).unwrap().unwrap();
+        assert!(history.is_saved());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn prune_before() {
+        let mut target = String::new();
+        let mut history = History::new();
+        history.apply(&mut target, Add('a')).unwrap();
+        history.apply(&mut target, Add('b')).unwrap();
+        history.undo(&mut target).unwrap().unwrap();
+        history.apply(&mut target, Add('c')).unwrap();
+        history.undo(&mut target).unwrap().unwrap();
+        history.apply(&mut target, Add('d')).unwrap();
+        history.apply(&mut target, Add('e')).unwrap();
+        history.undo(&mut target).unwrap().unwrap();
+        history.apply(&mut target, Add('f')).unwrap();
+        // Move the timestamps well apart so the test does not depend on the clock resolution.
+        let cutoff = Utc::now();
+        let old = cutoff - chrono::Duration::seconds(1);
+        history
+            .branches
+            .values_mut()
+            .flat_map(|branch| branch.entries.iter_mut())
+            .chain(history.record.entries.iter_mut())
+            .for_each(|entry| entry.timestamp = old);
+        history.record.entries.back_mut().unwrap().timestamp =
+            cutoff + chrono::Duration::seconds(1);
+        assert_eq!(history.branches().count(), 4);
+        assert_eq!(history.prune_before(&cutoff), 3);
+        assert_eq!(history.branches().count(), 1);
+        assert_eq!(target, "adf");
+    }
 }
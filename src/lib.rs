@@ -127,6 +127,10 @@ pub trait Action {
     /// and `Err` if something went wrong.
     ///
     /// The default implementation uses the [`apply`](trait.Action.html#tymethod.apply) implementation.
+    ///
+    /// Override this when reapplying must differ from the first application. For example,
+    /// an action that allocates an id in `apply` and releases it in `undo` should take the
+    /// same id back in `redo`, so that references to it stay valid.
     fn redo(&mut self, target: &mut Self::Target) -> Result<Self> {
         self.apply(target)
     }
@@ -163,7 +167,7 @@ pub enum Merged {
     serde(crate = "serde_crate")
 )]
 #[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
-struct At {
+pub struct At {
     branch: usize,
     current: usize,
 }
@@ -174,6 +178,16 @@ impl At {
     const fn new(branch: usize, current: usize) -> At {
         At { branch, current }
     }
+
+    /// Returns the branch of the position.
+    pub const fn branch(self) -> usize {
+        self.branch
+    }
+
+    /// Returns the current action of the position in the branch.
+    pub const fn current(self) -> usize {
+        self.current
+    }
 }
 
 /// The signal used for communicating state changes.
@@ -71,7 +71,6 @@ use {
         bound(serialize = "A: Serialize", deserialize = "A: Deserialize<'de>")
     )
 )]
-#[derive(Clone)]
 pub struct Record<A, F = Box<dyn FnMut(Signal)>> {
     pub(crate) entries: VecDeque<Entry<A>>,
     current: usize,
@@ -85,6 +84,31 @@ impl<A> Record<A> {
     pub fn new() -> Record<A> {
         Builder::new().build()
     }
+
+    /// Returns a record with the actions, current position and saved position
+    /// returned by [`into_parts`](struct.Record.html#method.into_parts).
+    ///
+    /// The actions are not applied and never merged. The target must be in the state it was
+    /// in at `current`, see [`push_applied`](struct.Record.html#method.push_applied).
+    /// A saved position that is out of bounds is ignored.
+    ///
+    /// # Panics
+    /// Panics if `current` is greater than the number of actions.
+    pub fn from_parts(
+        actions: impl IntoIterator<Item = A>,
+        current: usize,
+        saved: Option<usize>,
+    ) -> Record<A> {
+        let mut record = Record::new();
+        record.entries = actions.into_iter().map(Entry::from).collect();
+        assert!(
+            current <= record.len(),
+            "current can not be greater than the number of actions"
+        );
+        record.current = current;
+        record.saved = saved.filter(|&saved| saved <= record.len());
+        record
+    }
 }
 
 impl<A, F> Record<A, F> {
@@ -145,7 +169,7 @@ impl<A, F> Record<A, F> {
 
     /// Returns `true` if the target is in a saved state, `false` otherwise.
     pub fn is_saved(&self) -> bool {
-        self.saved.map_or(false, |saved| saved == self.current())
+        self.saved == Some(self.current())
     }
 
     /// Returns the position of the current action.
@@ -153,18 +177,90 @@ impl<A, F> Record<A, F> {
         self.current
     }
 
+    /// Returns the position marked as the saved state, or `None` if there is no saved state.
+    pub fn saved(&self) -> Option<usize> {
+        self.saved
+    }
+
+    /// Returns an iterator over the actions in the record, from the oldest to the newest.
+    ///
+    /// The action at index `i` is currently applied to the target if `i` is less than
+    /// [`current`](struct.Record.html#method.current), and undone otherwise.
+    pub fn actions(&self) -> impl Iterator<Item = &A> {
+        self.entries.iter().map(|e| &e.action)
+    }
+
+    /// Returns the action which will be undone in the next call to
+    /// [`undo`](struct.Record.html#method.undo).
+    pub fn peek_undo(&self) -> Option<&A> {
+        self.current
+            .checked_sub(1)
+            .and_then(|i| self.entries.get(i))
+            .map(|e| &e.action)
+    }
+
+    /// Returns the action which will be redone in the next call to
+    /// [`redo`](struct.Record.html#method.redo).
+    pub fn peek_redo(&self) -> Option<&A> {
+        self.entries.get(self.current).map(|e| &e.action)
+    }
+
+    /// Returns the oldest action in the record.
+    pub fn first(&self) -> Option<&A> {
+        self.entries.front().map(|e| &e.action)
+    }
+
+    /// Returns the newest action in the record, regardless of the current position.
+    pub fn last(&self) -> Option<&A> {
+        self.entries.back().map(|e| &e.action)
+    }
+
+    /// Returns the position right after the oldest action that matches the predicate.
+    ///
+    /// Passing the position to [`go_to`](struct.Record.html#method.go_to)
+    /// moves the target to the state where that action was the last one applied.
+    pub fn find(&self, mut f: impl FnMut(&A) -> bool) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|e| f(&e.action))
+            .map(|i| i + 1)
+    }
+
+    /// Consumes the record, returning the actions from the oldest to the newest,
+    /// the current position, and the saved position.
+    ///
+    /// The record can be rebuilt with [`from_parts`](struct.Record.html#method.from_parts).
+    pub fn into_parts(self) -> (Vec<A>, usize, Option<usize>) {
+        let actions = self.entries.into_iter().map(|e| e.action).collect();
+        (actions, self.current, self.saved)
+    }
+
+    /// Returns the number of undo and redo calls, in that order, that
+    /// [`go_to`](struct.Record.html#method.go_to) would make to reach `current`.
+    ///
+    /// Returns `None` if `current` is out of bounds.
+    pub fn plan_go_to(&self, current: usize) -> Option<(usize, usize)> {
+        if current > self.len() {
+            None
+        } else if current < self.current() {
+            Some((self.current() - current, 0))
+        } else {
+            Some((0, current - self.current()))
+        }
+    }
+
     /// Returns a queue.
-    pub fn queue(&mut self) -> Queue<A, F> {
+    pub fn queue(&mut self) -> Queue<'_, A, F> {
         Queue::from(self)
     }
 
     /// Returns a checkpoint.
-    pub fn checkpoint(&mut self) -> Checkpoint<A, F> {
+    pub fn checkpoint(&mut self) -> Checkpoint<'_, A, F> {
         Checkpoint::from(self)
     }
 
     /// Returns a structure for configurable formatting of the record.
-    pub fn display(&self) -> Display<A, F> {
+    pub fn display(&self) -> Display<'_, A, F> {
         Display::from(self)
     }
 }
@@ -177,7 +273,62 @@ impl<A: Action, F: FnMut(Signal)> Record<A, F> {
     ///
     /// [`apply`]: trait.Action.html#tymethod.apply
     pub fn apply(&mut self, target: &mut A::Target, action: A) -> Result<A> {
-        self.__apply(target, action).map(|(output, _, _)| output)
+        self.__apply(target, action, true)
+            .map(|(output, _, _)| output)
+    }
+
+    /// Like [`apply`](struct.Record.html#method.apply), but also returns how the action was
+    /// added to the record.
+    ///
+    /// [`Merged::No`] means the action was pushed as a new entry at `current() - 1`,
+    /// which also removes the oldest action if the limit was reached.
+    /// [`Merged::Yes`] means it was merged into the entry at `current() - 1`, and
+    /// [`Merged::Annul`] means it annulled the entry that was on top of the record.
+    ///
+    /// # Errors
+    /// If an error occur when executing [`apply`] the error is returned.
+    ///
+    /// [`apply`]: trait.Action.html#tymethod.apply
+    /// [`Merged::No`]: enum.Merged.html#variant.No
+    /// [`Merged::Yes`]: enum.Merged.html#variant.Yes
+    /// [`Merged::Annul`]: enum.Merged.html#variant.Annul
+    pub fn apply_report(
+        &mut self,
+        target: &mut A::Target,
+        action: A,
+    ) -> core::result::Result<(A::Output, Merged), A::Error> {
+        self.__apply(target, action, true)
+            .map(|(output, merged, _)| (output, merged))
+    }
+
+    /// Pushes the action on top of the record without executing its [`apply`] method.
+    ///
+    /// This is meant for restoring a record together with a target that already reflects the
+    /// effect of the action, e.g. when both are loaded from disk. The action is never merged,
+    /// so the actions are stored exactly as they are pushed. The redo actions are removed just
+    /// like in [`apply`](struct.Record.html#method.apply).
+    ///
+    /// **The record can not check that the target is in the state the action left it in.
+    /// If it is not, later calls to undo and redo will corrupt the target.**
+    ///
+    /// [`apply`]: trait.Action.html#tymethod.apply
+    pub fn push_applied(&mut self, action: A) {
+        self.__push(Entry::from(action), false);
+    }
+
+    /// Pushes the action on top of the record and executes its [`apply`] method,
+    /// without trying to merge it with the previous action.
+    ///
+    /// This starts a new undo step even if the action would normally be merged,
+    /// e.g. after a programmatic change in between two user edits.
+    ///
+    /// # Errors
+    /// If an error occur when executing [`apply`] the error is returned.
+    ///
+    /// [`apply`]: trait.Action.html#tymethod.apply
+    pub fn apply_no_merge(&mut self, target: &mut A::Target, action: A) -> Result<A> {
+        self.__apply(target, action, false)
+            .map(|(output, _, _)| output)
     }
 
     #[allow(clippy::type_complexity)]
@@ -185,8 +336,18 @@ impl<A: Action, F: FnMut(Signal)> Record<A, F> {
         &mut self,
         target: &mut A::Target,
         mut action: A,
-    ) -> core::result::Result<(A::Output, bool, VecDeque<Entry<A>>), A::Error> {
+        merge: bool,
+    ) -> core::result::Result<(A::Output, Merged, VecDeque<Entry<A>>), A::Error> {
         let output = action.apply(target)?;
+        let (merged, tail) = self.__push(Entry::from(action), merge);
+        Ok((output, merged, tail))
+    }
+
+    pub(crate) fn __push(
+        &mut self,
+        mut entry: Entry<A>,
+        merge: bool,
+    ) -> (Merged, VecDeque<Entry<A>>) {
         let current = self.current();
         let could_undo = self.can_undo();
         let could_redo = self.can_redo();
@@ -197,14 +358,14 @@ impl<A: Action, F: FnMut(Signal)> Record<A, F> {
         self.saved = self.saved.filter(|&saved| saved <= current);
         // Try to merge actions unless the target is in a saved state.
         let merged = match self.entries.back_mut() {
-            Some(last) if !was_saved => last.action.merge(&mut action),
+            Some(last) if merge && !was_saved => last.action.merge(&mut entry.action),
             _ => Merged::No,
         };
-        let merged_or_annulled = match merged {
-            Merged::Yes => true,
+        match merged {
+            Merged::Yes => (),
             Merged::Annul => {
                 self.entries.pop_back();
-                true
+                self.current -= 1;
             }
             // If actions are not merged or annulled push it onto the record.
             Merged::No => {
@@ -215,14 +376,17 @@ impl<A: Action, F: FnMut(Signal)> Record<A, F> {
                 } else {
                     self.current += 1;
                 }
-                self.entries.push_back(Entry::from(action));
-                false
+                self.entries.push_back(entry);
             }
         };
+        let can_undo = self.can_undo();
+        let is_saved = self.is_saved();
         self.slot.emit_if(could_redo, Signal::Redo(false));
-        self.slot.emit_if(!could_undo, Signal::Undo(true));
-        self.slot.emit_if(was_saved, Signal::Saved(false));
-        Ok((output, merged_or_annulled, tail))
+        self.slot
+            .emit_if(could_undo != can_undo, Signal::Undo(can_undo));
+        self.slot
+            .emit_if(was_saved != is_saved, Signal::Saved(is_saved));
+        (merged, tail)
     }
 
     /// Calls the [`undo`] method for the active action and sets
@@ -270,6 +434,52 @@ impl<A: Action, F: FnMut(Signal)> Record<A, F> {
         })
     }
 
+    /// Undoes the active action and replaces it with `action`, which is applied in its place.
+    ///
+    /// Unlike [`apply`](struct.Record.html#method.apply) the new action is never merged,
+    /// and the number of actions that can be undone stays the same.
+    /// Returns `None` if there is no action to replace.
+    ///
+    /// # Errors
+    /// If an error occur when executing [`undo`] or [`apply`] the error is returned.
+    /// If [`apply`] fails, the replaced action is left undone.
+    ///
+    /// [`undo`]: trait.Action.html#tymethod.undo
+    /// [`apply`]: trait.Action.html#tymethod.apply
+    pub fn replace_last(&mut self, target: &mut A::Target, mut action: A) -> Option<Result<A>> {
+        if !self.can_undo() {
+            return None;
+        }
+        let could_undo = self.can_undo();
+        let could_redo = self.can_redo();
+        let was_saved = self.is_saved();
+        // Temporarily remove slot so only the net change is signaled.
+        let slot = self.disconnect();
+        let result = self
+            .undo(target)
+            .unwrap()
+            .and_then(|_| action.apply(target));
+        if result.is_ok() {
+            let current = self.current();
+            self.entries.truncate(current);
+            self.saved = self.saved.filter(|&saved| saved <= current);
+            self.entries.push_back(Entry::from(action));
+            self.current += 1;
+        }
+        // Add slot back.
+        self.slot.f = slot;
+        let can_undo = self.can_undo();
+        let can_redo = self.can_redo();
+        let is_saved = self.is_saved();
+        self.slot
+            .emit_if(could_undo != can_undo, Signal::Undo(can_undo));
+        self.slot
+            .emit_if(could_redo != can_redo, Signal::Redo(can_redo));
+        self.slot
+            .emit_if(was_saved != is_saved, Signal::Saved(is_saved));
+        Some(result)
+    }
+
     /// Marks the target as currently being in a saved or unsaved state.
     pub fn set_saved(&mut self, saved: bool) {
         let was_saved = self.is_saved();
@@ -282,16 +492,83 @@ impl<A: Action, F: FnMut(Signal)> Record<A, F> {
         }
     }
 
+    /// Marks the target as being in a saved state at `current`, without moving there.
+    ///
+    /// Does nothing if `current` is out of bounds.
+    pub fn set_saved_at(&mut self, current: usize) {
+        if current > self.len() {
+            return;
+        }
+        let was_saved = self.is_saved();
+        self.saved = Some(current);
+        let is_saved = self.is_saved();
+        self.slot
+            .emit_if(was_saved != is_saved, Signal::Saved(is_saved));
+    }
+
     /// Removes all actions from the record without undoing them.
     pub fn clear(&mut self) {
         let could_undo = self.can_undo();
         let could_redo = self.can_redo();
         self.entries.clear();
-        self.saved = self.is_saved().then(|| 0);
+        self.saved = self.is_saved().then_some(0);
         self.current = 0;
         self.slot.emit_if(could_undo, Signal::Undo(false));
         self.slot.emit_if(could_redo, Signal::Redo(false));
     }
+
+    /// Shortens the record to `len` actions by removing the newest undone actions.
+    ///
+    /// Only actions that can be redone are removed, so if `len` is less than the current position
+    /// the record is truncated at the current position instead.
+    /// If `len` is greater than the length of the record, this has no effect.
+    pub fn truncate(&mut self, len: usize) {
+        let could_undo = self.can_undo();
+        let could_redo = self.can_redo();
+        let was_saved = self.is_saved();
+        let len = len.max(self.current);
+        self.entries.truncate(len);
+        self.saved = self.saved.filter(|&saved| saved <= len);
+        let can_undo = self.can_undo();
+        let can_redo = self.can_redo();
+        let is_saved = self.is_saved();
+        self.slot
+            .emit_if(could_undo != can_undo, Signal::Undo(can_undo));
+        self.slot
+            .emit_if(could_redo != can_redo, Signal::Redo(can_redo));
+        self.slot
+            .emit_if(was_saved != is_saved, Signal::Saved(is_saved));
+    }
+
+    /// Sets the `limit` of the record.
+    ///
+    /// If the record is longer than the new limit, the oldest actions are removed without
+    /// being undone, up to the current position. If that is not enough, the newest undone
+    /// actions are removed as well.
+    ///
+    /// # Panics
+    /// Panics if `limit` is `0`.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = NonZeroUsize::new(limit).expect("limit can not be `0`");
+        let could_undo = self.can_undo();
+        let could_redo = self.can_redo();
+        let was_saved = self.is_saved();
+        let evict = self.len().saturating_sub(limit).min(self.current);
+        self.entries.drain(..evict);
+        self.current -= evict;
+        self.saved = self.saved.and_then(|saved| saved.checked_sub(evict));
+        self.entries.truncate(limit);
+        self.saved = self.saved.filter(|&saved| saved <= limit);
+        let can_undo = self.can_undo();
+        let can_redo = self.can_redo();
+        let is_saved = self.is_saved();
+        self.slot
+            .emit_if(could_undo != can_undo, Signal::Undo(can_undo));
+        self.slot
+            .emit_if(could_redo != can_redo, Signal::Redo(can_redo));
+        self.slot
+            .emit_if(was_saved != is_saved, Signal::Saved(is_saved));
+    }
 }
 
 impl<A: Action<Output = ()>, F: FnMut(Signal)> Record<A, F> {
@@ -342,6 +619,123 @@ impl<A: Action<Output = ()>, F: FnMut(Signal)> Record<A, F> {
         Some(Ok(()))
     }
 
+    /// Calls [`apply`](struct.Record.html#method.apply) for each action in `actions`, in order.
+    ///
+    /// The actions are merged and signaled exactly as if they were applied one by one,
+    /// so a run of actions that merge with each other ends up as a single undo step.
+    ///
+    /// # Errors
+    /// If an error occur when executing [`apply`] the error is returned
+    /// and the remaining actions are not applied.
+    ///
+    /// [`apply`]: trait.Action.html#tymethod.apply
+    pub fn apply_iter(
+        &mut self,
+        target: &mut A::Target,
+        actions: impl IntoIterator<Item = A>,
+    ) -> Result<A> {
+        actions
+            .into_iter()
+            .try_for_each(|action| self.apply(target, action))
+    }
+
+    /// Calls [`apply`](struct.Record.html#method.apply) for each action in `actions` as one batch.
+    ///
+    /// The first action is never merged with the action before the batch, so the batch can
+    /// always be rolled back. The rest of the actions are merged as usual.
+    ///
+    /// A successful batch is **not** guaranteed to become a single entry in the record. It only
+    /// does if every action merges into the first one. Otherwise each action that was not merged
+    /// becomes its own entry, and is undone and redone on its own.
+    ///
+    /// # Errors
+    /// If an error occur when executing [`apply`], the actions of the batch that were already
+    /// applied are undone and removed, and the error is returned. If an error occur when
+    /// undoing them, that error is returned instead.
+    ///
+    /// [`apply`]: trait.Action.html#tymethod.apply
+    pub fn apply_batch(
+        &mut self,
+        target: &mut A::Target,
+        actions: impl IntoIterator<Item = A>,
+    ) -> Result<A> {
+        let mut checkpoint = self.checkpoint();
+        for action in actions {
+            if let Err(err) = checkpoint.apply(target, action) {
+                if let Some(Err(err)) = checkpoint.cancel(target) {
+                    return Err(err);
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls [`undo`] until the start of the record is reached.
+    ///
+    /// # Errors
+    /// If an error occur when executing [`undo`] the error is returned
+    /// and the record is left at the position of the failing action.
+    ///
+    /// [`undo`]: trait.Action.html#tymethod.undo
+    pub fn undo_all(&mut self, target: &mut A::Target) -> Result<A> {
+        self.go_to(target, 0).unwrap()
+    }
+
+    /// Calls [`redo`] until the end of the record is reached.
+    ///
+    /// # Errors
+    /// If an error occur when executing [`redo`] the error is returned
+    /// and the record is left at the position of the failing action.
+    ///
+    /// [`redo`]: trait.Action.html#method.redo
+    pub fn redo_all(&mut self, target: &mut A::Target) -> Result<A> {
+        self.go_to(target, self.len()).unwrap()
+    }
+
+    /// Sets the target to `snapshot` and calls [`redo`] until the current position is reached again.
+    ///
+    /// The snapshot must be the state of the target at `current`. This can be used to
+    /// recompute the target after an external change without replaying the whole record.
+    /// Returns `None` if `current` is ahead of the current position.
+    ///
+    /// # Errors
+    /// If an error occur when executing [`redo`] the error is returned
+    /// and the record is left at the position of the failing action.
+    ///
+    /// [`redo`]: trait.Action.html#method.redo
+    pub fn reapply_from(
+        &mut self,
+        target: &mut A::Target,
+        current: usize,
+        snapshot: A::Target,
+    ) -> Option<Result<A>> {
+        if current > self.current() {
+            return None;
+        }
+        let old = self.current();
+        let could_undo = self.can_undo();
+        let could_redo = self.can_redo();
+        let was_saved = self.is_saved();
+        // Temporarily remove slot so only the net change is signaled.
+        let slot = self.disconnect();
+        *target = snapshot;
+        self.current = current;
+        let result = self.go_to(target, old).unwrap();
+        // Add slot back.
+        self.slot.f = slot;
+        let can_undo = self.can_undo();
+        let can_redo = self.can_redo();
+        let is_saved = self.is_saved();
+        self.slot
+            .emit_if(could_undo != can_undo, Signal::Undo(can_undo));
+        self.slot
+            .emit_if(could_redo != can_redo, Signal::Redo(can_redo));
+        self.slot
+            .emit_if(was_saved != is_saved, Signal::Saved(is_saved));
+        Some(result)
+    }
+
     /// Go back or forward in the record to the action that was made closest to the datetime provided.
     #[cfg(feature = "chrono")]
     pub fn time_travel(&mut self, target: &mut A::Target, to: &DateTime<Utc>) -> Option<Result<A>> {
@@ -388,6 +782,27 @@ impl<A: ToString, F> Record<A, F> {
     }
 }
 
+impl<A: Clone, F: Clone> Clone for Record<A, F> {
+    fn clone(&self) -> Self {
+        Record {
+            entries: self.entries.clone(),
+            current: self.current,
+            limit: self.limit,
+            saved: self.saved,
+            slot: self.slot.clone(),
+        }
+    }
+
+    /// Reuses the allocated storage of `self` when cloning the actions of `source`.
+    fn clone_from(&mut self, source: &Self) {
+        self.entries.clone_from(&source.entries);
+        self.current = source.current;
+        self.limit = source.limit;
+        self.saved = source.saved;
+        self.slot.clone_from(&source.slot);
+    }
+}
+
 impl<A> Default for Record<A> {
     fn default() -> Record<A> {
         Record::new()
@@ -457,6 +872,11 @@ impl<F> Builder<F> {
     }
 
     /// Sets the capacity for the record.
+    ///
+    /// Setting the capacity to the same value as the [`limit`](struct.Builder.html#method.limit)
+    /// means the record never reallocates, since it can not grow past the limit.
+    /// Note that the memory for all `limit` actions is allocated up front, so this is
+    /// best suited for small limits.
     pub fn capacity(mut self, capacity: usize) -> Builder<F> {
         self.capacity = capacity;
         self
@@ -484,7 +904,7 @@ impl<F> Builder<F> {
             entries: VecDeque::with_capacity(self.capacity),
             current: 0,
             limit: self.limit,
-            saved: self.saved.then(|| 0),
+            saved: self.saved.then_some(0),
             slot: self.slot,
         }
     }
@@ -588,12 +1008,12 @@ impl<A: Action<Output = ()>, F: FnMut(Signal)> Queue<'_, A, F> {
     pub fn cancel(self) {}
 
     /// Returns a queue.
-    pub fn queue(&mut self) -> Queue<A, F> {
+    pub fn queue(&mut self) -> Queue<'_, A, F> {
         self.record.queue()
     }
 
     /// Returns a checkpoint.
-    pub fn checkpoint(&mut self) -> Checkpoint<A, F> {
+    pub fn checkpoint(&mut self) -> Checkpoint<'_, A, F> {
         self.record.checkpoint()
     }
 }
@@ -607,26 +1027,52 @@ impl<'a, A, F> From<&'a mut Record<A, F>> for Queue<'a, A, F> {
     }
 }
 
+impl<A, F> Extend<A> for Queue<'_, A, F> {
+    /// Queues an `apply` action for each action in the iterator.
+    ///
+    /// The actions are applied after any actions that are already queued, in the order of the iterator.
+    fn extend<I: IntoIterator<Item = A>>(&mut self, actions: I) {
+        self.actions
+            .extend(actions.into_iter().map(QueueAction::Apply));
+    }
+}
+
 #[derive(Debug)]
 enum CheckpointAction<A> {
-    Apply(Option<usize>, VecDeque<Entry<A>>),
+    Apply(Option<usize>, VecDeque<Entry<A>>, Merged),
     Undo,
     Redo,
 }
 
 /// Wraps a record and gives it checkpoint functionality.
+///
+/// Checkpoints can be nested by calling [`checkpoint`](struct.Checkpoint.html#method.checkpoint).
+/// Canceling a nested checkpoint only cancels its own changes. When a nested checkpoint is
+/// committed its changes become part of the outer checkpoint,
+/// so canceling the outer checkpoint cancels them too.
 #[derive(Debug)]
 pub struct Checkpoint<'a, A, F> {
     record: &'a mut Record<A, F>,
     actions: Vec<CheckpointAction<A>>,
+    parent: Option<&'a mut Vec<CheckpointAction<A>>>,
 }
 
 impl<A: Action<Output = ()>, F: FnMut(Signal)> Checkpoint<'_, A, F> {
     /// Calls the `apply` method.
+    ///
+    /// The action is only merged with the previous action if that was also applied
+    /// by this checkpoint, so canceling never has to split up an action from before it.
+    /// The first action of a checkpoint is therefore never merged with the action that
+    /// was on top before the checkpoint was created.
     pub fn apply(&mut self, target: &mut A::Target, action: A) -> Result<A> {
         let saved = self.record.saved;
-        let (_, _, tail) = self.record.__apply(target, action)?;
-        self.actions.push(CheckpointAction::Apply(saved, tail));
+        let merge = matches!(
+            self.actions.last(),
+            Some(CheckpointAction::Apply(_, _, Merged::Yes | Merged::No))
+        );
+        let (_, merged, tail) = self.record.__apply(target, action, merge)?;
+        self.actions
+            .push(CheckpointAction::Apply(saved, tail, merged));
         Ok(())
     }
 
@@ -652,25 +1098,53 @@ impl<A: Action<Output = ()>, F: FnMut(Signal)> Checkpoint<'_, A, F> {
         }
     }
 
+    /// Returns the number of actions in the checkpoint.
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    /// Returns `true` if the checkpoint is empty.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
     /// Commits the changes and consumes the checkpoint.
-    pub fn commit(self) {}
+    ///
+    /// If the checkpoint is nested, its changes are handed to the outer checkpoint.
+    pub fn commit(mut self) {
+        if let Some(parent) = self.parent {
+            parent.append(&mut self.actions);
+        }
+    }
 
     /// Cancels the changes and consumes the checkpoint.
     ///
     /// # Errors
     /// If an error occur when canceling the changes, the error is returned
     /// and the remaining actions are not canceled.
-    pub fn cancel(self, target: &mut A::Target) -> Option<Result<A>> {
-        for action in self.actions.into_iter().rev() {
+    pub fn cancel(mut self, target: &mut A::Target) -> Option<Result<A>> {
+        // The number of pushed actions that have since been annulled.
+        let mut annulled = 0;
+        while let Some(action) = self.actions.pop() {
             match action {
-                CheckpointAction::Apply(saved, mut entries) => match self.record.undo(target) {
-                    Some(Ok(())) => {
-                        self.record.entries.pop_back();
-                        self.record.entries.append(&mut entries);
-                        self.record.saved = saved;
+                // A merged action is undone together with the action it was merged into.
+                CheckpointAction::Apply(_, _, Merged::Yes) => (),
+                CheckpointAction::Apply(_, _, Merged::Annul) => annulled += 1,
+                CheckpointAction::Apply(saved, mut entries, Merged::No) if annulled > 0 => {
+                    annulled -= 1;
+                    self.record.entries.append(&mut entries);
+                    self.record.saved = saved;
+                }
+                CheckpointAction::Apply(saved, mut entries, Merged::No) => {
+                    match self.record.undo(target) {
+                        Some(Ok(())) => {
+                            self.record.entries.pop_back();
+                            self.record.entries.append(&mut entries);
+                            self.record.saved = saved;
+                        }
+                        o => return o,
                     }
-                    o => return o,
-                },
+                }
                 CheckpointAction::Undo => match self.record.redo(target) {
                     Some(Ok(())) => (),
                     o => return o,
@@ -685,13 +1159,17 @@ impl<A: Action<Output = ()>, F: FnMut(Signal)> Checkpoint<'_, A, F> {
     }
 
     /// Returns a queue.
-    pub fn queue(&mut self) -> Queue<A, F> {
+    pub fn queue(&mut self) -> Queue<'_, A, F> {
         self.record.queue()
     }
 
     /// Returns a checkpoint.
-    pub fn checkpoint(&mut self) -> Checkpoint<A, F> {
-        self.record.checkpoint()
+    pub fn checkpoint(&mut self) -> Checkpoint<'_, A, F> {
+        Checkpoint {
+            record: self.record,
+            actions: Vec::new(),
+            parent: Some(&mut self.actions),
+        }
     }
 }
 
@@ -700,6 +1178,7 @@ impl<'a, A, F> From<&'a mut Record<A, F>> for Checkpoint<'a, A, F> {
         Checkpoint {
             record,
             actions: Vec::new(),
+            parent: None,
         }
     }
 }
@@ -800,6 +1279,8 @@ mod tests {
     use crate::*;
     use alloc::boxed::Box;
     use alloc::string::String;
+    use alloc::vec;
+    use alloc::vec::Vec;
 
     struct Add(char);
 
@@ -819,6 +1300,37 @@ mod tests {
         }
     }
 
+    #[derive(Clone)]
+    struct Step(i32);
+
+    impl Action for Step {
+        type Target = i32;
+        type Output = ();
+        type Error = ();
+
+        fn apply(&mut self, n: &mut i32) -> Result<Step> {
+            if self.0 == 0 {
+                return Err(());
+            }
+            *n += self.0;
+            Ok(())
+        }
+
+        fn undo(&mut self, n: &mut i32) -> Result<Step> {
+            *n -= self.0;
+            Ok(())
+        }
+
+        fn merge(&mut self, step: &mut Self) -> Merged {
+            self.0 += step.0;
+            if self.0 == 0 {
+                Merged::Annul
+            } else {
+                Merged::Yes
+            }
+        }
+    }
+
     #[test]
     fn go_to() {
         let mut target = String::new();
@@ -851,6 +1363,289 @@ mod tests {
         assert_eq!(record.current(), 3);
     }
 
+    #[test]
+    fn actions() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        record.apply(&mut target, Add('a')).unwrap();
+        record.apply(&mut target, Add('b')).unwrap();
+        record.apply(&mut target, Add('c')).unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        let actions: String = record.actions().map(|a| a.0).collect();
+        assert_eq!(actions, "abc");
+    }
+
+    #[test]
+    fn undo_all() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        record.apply(&mut target, Add('a')).unwrap();
+        record.apply(&mut target, Add('b')).unwrap();
+        record.apply(&mut target, Add('c')).unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        record.undo_all(&mut target).unwrap();
+        assert_eq!(target, "");
+        assert_eq!(record.current(), 0);
+        record.redo_all(&mut target).unwrap();
+        assert_eq!(target, "abc");
+        assert_eq!(record.current(), 3);
+    }
+
+    #[test]
+    fn clone_from() {
+        fn parts<F>(record: &Record<Step, F>) -> (Vec<i32>, usize, Option<usize>, usize) {
+            let actions = record.actions().map(|s| s.0).collect();
+            (actions, record.current(), record.saved(), record.limit())
+        }
+
+        let mut target = 0;
+        let mut record: Record<Step, fn(Signal)> = record::Builder::new().limit(4).build();
+        record.apply(&mut target, Step(1)).unwrap();
+        record.set_saved(true);
+        record.apply(&mut target, Step(2)).unwrap();
+        record.apply_no_merge(&mut target, Step(3)).unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        let mut copy: Record<Step, fn(Signal)> = record::Builder::new().build();
+        copy.apply(&mut 0, Step(5)).unwrap();
+        copy.clone_from(&record);
+        assert_eq!(parts(&copy), parts(&record.clone()));
+        assert_eq!(parts(&copy), (vec![1, 2, 3], 2, Some(1), 4));
+    }
+
+    #[test]
+    fn first_last() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        assert!(record.first().is_none());
+        assert!(record.last().is_none());
+        record.apply(&mut target, Add('a')).unwrap();
+        record.apply(&mut target, Add('b')).unwrap();
+        record.apply(&mut target, Add('c')).unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        assert_eq!(record.first().unwrap().0, 'a');
+        assert_eq!(record.last().unwrap().0, 'c');
+    }
+
+    #[test]
+    fn truncate() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        record.apply(&mut target, Add('a')).unwrap();
+        record.apply(&mut target, Add('b')).unwrap();
+        record.apply(&mut target, Add('c')).unwrap();
+        record.apply(&mut target, Add('d')).unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        record.truncate(3);
+        assert_eq!(record.len(), 3);
+        assert_eq!(record.current(), 2);
+        record.truncate(2);
+        assert_eq!(record.len(), 2);
+        assert!(!record.can_redo());
+        record.truncate(0);
+        assert_eq!(record.len(), 2);
+        assert_eq!(record.current(), 2);
+        record.undo(&mut target).unwrap().unwrap();
+        assert_eq!(target, "a");
+    }
+
+    #[test]
+    fn replace_last() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        assert!(record.replace_last(&mut target, Add('a')).is_none());
+        record.apply(&mut target, Add('a')).unwrap();
+        record.apply(&mut target, Add('b')).unwrap();
+        record.apply(&mut target, Add('c')).unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        record.replace_last(&mut target, Add('x')).unwrap().unwrap();
+        record.replace_last(&mut target, Add('y')).unwrap().unwrap();
+        assert_eq!(target, "ay");
+        assert_eq!(record.peek_undo().unwrap().0, 'y');
+        assert!(record.peek_redo().is_none());
+        assert_eq!(record.len(), 2);
+        assert!(!record.can_redo());
+        record.undo(&mut target).unwrap().unwrap();
+        assert_eq!(target, "a");
+    }
+
+    #[test]
+    fn reapply_from() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        record.apply(&mut target, Add('a')).unwrap();
+        record.apply(&mut target, Add('b')).unwrap();
+        record.apply(&mut target, Add('c')).unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        target.clear();
+        record
+            .reapply_from(&mut target, 1, String::from("a"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(target, "ab");
+        assert_eq!(record.current(), 2);
+        assert!(record.reapply_from(&mut target, 3, String::new()).is_none());
+    }
+
+    #[test]
+    fn apply_iter() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        record
+            .apply_iter(&mut target, "abc".chars().map(Add))
+            .unwrap();
+        assert_eq!(target, "abc");
+        assert_eq!(record.len(), 3);
+    }
+
+    #[test]
+    fn apply_batch() {
+        let mut target = 0;
+        let mut record = Record::new();
+        record.apply(&mut target, Step(1)).unwrap();
+        record
+            .apply_batch(&mut target, vec![Step(2), Step(3)])
+            .unwrap();
+        assert_eq!(target, 6);
+        assert_eq!(record.len(), 2);
+        assert!(record
+            .apply_batch(&mut target, vec![Step(4), Step(0)])
+            .is_err());
+        assert_eq!(target, 6);
+        assert_eq!(record.len(), 2);
+        record.undo(&mut target).unwrap().unwrap();
+        assert_eq!(target, 1);
+    }
+
+    #[test]
+    fn apply_batch_no_merge() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        record
+            .apply_batch(&mut target, "abc".chars().map(Add))
+            .unwrap();
+        assert_eq!(target, "abc");
+        assert_eq!(record.len(), 3);
+        record.undo(&mut target).unwrap().unwrap();
+        assert_eq!(target, "ab");
+    }
+
+    #[test]
+    fn set_limit() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        record
+            .apply_iter(&mut target, "abcde".chars().map(Add))
+            .unwrap();
+        record.go_to(&mut target, 3).unwrap().unwrap();
+        record.set_saved(true);
+        record.set_limit(4);
+        assert_eq!(record.limit(), 4);
+        assert_eq!(record.len(), 4);
+        assert_eq!(record.current(), 2);
+        assert_eq!(record.saved(), Some(2));
+        record.set_limit(1);
+        assert_eq!(record.len(), 1);
+        assert_eq!(record.current(), 0);
+        assert_eq!(record.saved(), Some(0));
+        assert!(!record.can_undo());
+        record.redo(&mut target).unwrap().unwrap();
+        assert_eq!(target, "abcd");
+        record.set_limit(10);
+        assert_eq!(record.len(), 1);
+        record.apply(&mut target, Add('f')).unwrap();
+        assert_eq!(record.len(), 2);
+    }
+
+    #[test]
+    fn find() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        record.apply(&mut target, Add('a')).unwrap();
+        record.apply(&mut target, Add('b')).unwrap();
+        record.apply(&mut target, Add('c')).unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        assert_eq!(record.find(|a| a.0 == 'a'), Some(1));
+        assert_eq!(record.find(|a| a.0 == 'c'), Some(3));
+        assert_eq!(record.find(|a| a.0 == 'd'), None);
+    }
+
+    #[test]
+    fn into_parts() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        record.apply(&mut target, Add('a')).unwrap();
+        record.apply(&mut target, Add('b')).unwrap();
+        record.set_saved(true);
+        record.apply(&mut target, Add('c')).unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        let (actions, current, saved) = record.into_parts();
+        assert_eq!(actions.len(), 3);
+        assert_eq!(current, 2);
+        assert_eq!(saved, Some(2));
+    }
+
+    #[test]
+    fn from_parts() {
+        let mut target = 0;
+        let mut record = Record::new();
+        record.apply(&mut target, Step(1)).unwrap();
+        record.set_saved(true);
+        record.apply(&mut target, Step(2)).unwrap();
+        record.apply_no_merge(&mut target, Step(4)).unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        let (actions, current, saved) = record.into_parts();
+        let mut record = Record::from_parts(actions, current, saved);
+        assert_eq!(record.len(), 3);
+        assert_eq!(record.current(), 2);
+        assert_eq!(record.saved(), Some(1));
+        assert!(!record.is_saved());
+        record.undo(&mut target).unwrap().unwrap();
+        assert!(record.is_saved());
+        assert_eq!(target, 1);
+        record.go_to(&mut target, 3).unwrap().unwrap();
+        assert_eq!(target, 7);
+    }
+
+    #[test]
+    fn push_applied() {
+        let mut target = String::from("ab");
+        let mut record = Record::new();
+        record.push_applied(Add('a'));
+        record.push_applied(Add('b'));
+        assert_eq!(record.current(), 2);
+        record.undo(&mut target).unwrap().unwrap();
+        record.undo(&mut target).unwrap().unwrap();
+        assert_eq!(target, "");
+        record.redo_all(&mut target).unwrap();
+        assert_eq!(target, "ab");
+    }
+
+    #[test]
+    fn apply_report() {
+        let mut target = 0;
+        let mut record = Record::new();
+        let (_, merged) = record.apply_report(&mut target, Step(1)).unwrap();
+        assert_eq!(merged, Merged::No);
+        let (_, merged) = record.apply_report(&mut target, Step(2)).unwrap();
+        assert_eq!(merged, Merged::Yes);
+        let (_, merged) = record.apply_report(&mut target, Step(-3)).unwrap();
+        assert_eq!(merged, Merged::Annul);
+        assert!(record.is_empty());
+    }
+
+    #[test]
+    fn push_applied_no_merge() {
+        let mut target = 3;
+        let mut record = Record::new();
+        record.push_applied(Step(1));
+        record.push_applied(Step(2));
+        assert_eq!(record.len(), 2);
+        record.undo(&mut target).unwrap().unwrap();
+        assert_eq!(target, 1);
+    }
+
     #[test]
     fn queue_commit() {
         let mut target = String::new();
@@ -876,6 +1671,18 @@ mod tests {
         assert_eq!(target, "abc");
     }
 
+    #[test]
+    fn queue_extend() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        let mut queue = record.queue();
+        queue.apply(Add('a'));
+        queue.extend(vec![Add('b'), Add('c')]);
+        queue.undo();
+        queue.commit(&mut target).unwrap().unwrap();
+        assert_eq!(target, "ab");
+    }
+
     #[test]
     fn checkpoint_commit() {
         let mut target = String::new();
@@ -901,6 +1708,29 @@ mod tests {
         assert_eq!(target, "abcdefghi");
     }
 
+    #[test]
+    fn checkpoint_len() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        let mut cp = record.checkpoint();
+        assert!(cp.is_empty());
+        cp.apply(&mut target, Add('a')).unwrap();
+        cp.apply(&mut target, Add('b')).unwrap();
+        cp.undo(&mut target).unwrap().unwrap();
+        assert_eq!(cp.len(), 3);
+        assert!(!cp.is_empty());
+    }
+
+    #[test]
+    fn checkpoint_drop() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        let mut cp = record.checkpoint();
+        cp.apply(&mut target, Add('a')).unwrap();
+        // The record can be used again once the checkpoint is no longer used.
+        assert_eq!(record.len(), 1);
+    }
+
     #[test]
     fn checkpoint_cancel() {
         let mut target = String::new();
@@ -926,6 +1756,66 @@ mod tests {
         assert_eq!(target, "");
     }
 
+    #[test]
+    fn set_saved_at() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        record.apply(&mut target, Add('a')).unwrap();
+        record.apply(&mut target, Add('b')).unwrap();
+        record.set_saved_at(1);
+        assert!(!record.is_saved());
+        record.set_saved_at(3);
+        assert_eq!(record.saved(), Some(1));
+        record.undo(&mut target).unwrap().unwrap();
+        assert!(record.is_saved());
+    }
+
+    #[test]
+    fn checkpoint_annul() {
+        let mut target = 0;
+        let mut record = Record::new();
+        record.apply_no_merge(&mut target, Step(1)).unwrap();
+        record.apply_no_merge(&mut target, Step(2)).unwrap();
+        let mut cp = record.checkpoint();
+        cp.apply(&mut target, Step(-2)).unwrap();
+        cp.apply(&mut target, Step(5)).unwrap();
+        cp.apply(&mut target, Step(-3)).unwrap();
+        cp.apply(&mut target, Step(4)).unwrap();
+        assert_eq!(target, 7);
+        cp.cancel(&mut target).unwrap().unwrap();
+        assert_eq!(target, 3);
+        assert_eq!(record.len(), 2);
+        assert_eq!(record.current(), 2);
+    }
+
+    #[test]
+    fn checkpoint_nested() {
+        let mut target = String::new();
+        let mut record = Record::new();
+        let mut outer = record.checkpoint();
+        outer.apply(&mut target, Add('a')).unwrap();
+        let mut inner = outer.checkpoint();
+        inner.apply(&mut target, Add('b')).unwrap();
+        inner.cancel(&mut target).unwrap().unwrap();
+        assert_eq!(target, "a");
+        let mut inner = outer.checkpoint();
+        inner.apply(&mut target, Add('c')).unwrap();
+        inner.commit();
+        assert_eq!(outer.len(), 2);
+        outer.commit();
+        assert_eq!(target, "ac");
+        assert_eq!(record.len(), 2);
+
+        let mut outer = record.checkpoint();
+        outer.apply(&mut target, Add('d')).unwrap();
+        let mut inner = outer.checkpoint();
+        inner.apply(&mut target, Add('e')).unwrap();
+        inner.commit();
+        outer.cancel(&mut target).unwrap().unwrap();
+        assert_eq!(target, "ac");
+        assert_eq!(record.len(), 2);
+    }
+
     #[test]
     fn checkpoint_saved() {
         let mut target = String::new();
@@ -951,6 +1841,36 @@ mod tests {
         assert_eq!(target, "abc");
     }
 
+    #[test]
+    fn annul() {
+        let mut target = 0;
+        let mut record = Record::new();
+        record.apply(&mut target, Step(1)).unwrap();
+        record.apply_no_merge(&mut target, Step(2)).unwrap();
+        record.apply(&mut target, Step(-2)).unwrap();
+        assert_eq!(target, 1);
+        assert_eq!(record.len(), 1);
+        assert_eq!(record.current(), 1);
+        record.undo(&mut target).unwrap().unwrap();
+        assert_eq!(target, 0);
+        assert!(!record.can_undo());
+    }
+
+    #[test]
+    fn apply_no_merge() {
+        let mut target = 0;
+        let mut record = Record::new();
+        record.apply(&mut target, Step(1)).unwrap();
+        record.apply(&mut target, Step(2)).unwrap();
+        assert_eq!(record.len(), 1);
+        record.apply_no_merge(&mut target, Step(3)).unwrap();
+        record.apply(&mut target, Step(4)).unwrap();
+        assert_eq!(target, 10);
+        assert_eq!(record.len(), 2);
+        record.undo(&mut target).unwrap().unwrap();
+        assert_eq!(target, 3);
+    }
+
     #[test]
     fn dyn_trait() {
         let _: Box<dyn Action<Output = (), Error = &'static str, Target = String>> =
@@ -125,7 +125,7 @@ impl<A, F, const LIMIT: usize> Timeline<A, F, LIMIT> {
 
     /// Returns `true` if the target is in a saved state, `false` otherwise.
     pub fn is_saved(&self) -> bool {
-        self.saved.map_or(false, |saved| saved == self.current())
+        self.saved == Some(self.current())
     }
 
     /// Returns the position of the current action.
@@ -135,7 +135,7 @@ impl<A, F, const LIMIT: usize> Timeline<A, F, LIMIT> {
 
     /// Returns a structure for configurable formatting of the record.
     #[cfg(feature = "alloc")]
-    pub fn display(&self) -> Display<A, F, LIMIT> {
+    pub fn display(&self) -> Display<'_, A, F, LIMIT> {
         Display::from(self)
     }
 }
@@ -166,6 +166,7 @@ impl<A: Action, F: FnMut(Signal), const LIMIT: usize> Timeline<A, F, LIMIT> {
             Merged::Yes => (),
             Merged::Annul => {
                 self.entries.pop();
+                self.current -= 1;
             }
             // If actions are not merged or annulled push it onto the record.
             Merged::No => {
@@ -179,9 +180,13 @@ impl<A: Action, F: FnMut(Signal), const LIMIT: usize> Timeline<A, F, LIMIT> {
                 self.entries.push(Entry::from(action));
             }
         };
+        let can_undo = self.can_undo();
+        let is_saved = self.is_saved();
         self.slot.emit_if(could_redo, Signal::Redo(false));
-        self.slot.emit_if(!could_undo, Signal::Undo(true));
-        self.slot.emit_if(was_saved, Signal::Saved(false));
+        self.slot
+            .emit_if(could_undo != can_undo, Signal::Undo(can_undo));
+        self.slot
+            .emit_if(was_saved != is_saved, Signal::Saved(is_saved));
         Ok(output)
     }
 
@@ -247,7 +252,7 @@ impl<A: Action, F: FnMut(Signal), const LIMIT: usize> Timeline<A, F, LIMIT> {
         let could_undo = self.can_undo();
         let could_redo = self.can_redo();
         self.entries.clear();
-        self.saved = self.is_saved().then(|| 0);
+        self.saved = self.is_saved().then_some(0);
         self.current = 0;
         self.slot.emit_if(could_undo, Signal::Undo(false));
         self.slot.emit_if(could_redo, Signal::Redo(false));
@@ -377,7 +382,7 @@ impl<F> Builder<F> {
         Timeline {
             entries: ArrayVec::new(),
             current: 0,
-            saved: self.saved.then(|| 0),
+            saved: self.saved.then_some(0),
             slot: self.slot,
         }
     }
@@ -516,6 +521,33 @@ mod tests {
         }
     }
 
+    struct Step(i32);
+
+    impl Action for Step {
+        type Target = i32;
+        type Output = ();
+        type Error = ();
+
+        fn apply(&mut self, n: &mut i32) -> Result<Step> {
+            *n += self.0;
+            Ok(())
+        }
+
+        fn undo(&mut self, n: &mut i32) -> Result<Step> {
+            *n -= self.0;
+            Ok(())
+        }
+
+        fn merge(&mut self, step: &mut Self) -> Merged {
+            self.0 += step.0;
+            if self.0 == 0 {
+                Merged::Annul
+            } else {
+                Merged::Yes
+            }
+        }
+    }
+
     #[test]
     fn limit() {
         let mut target = ArrayString::new();
@@ -526,4 +558,18 @@ mod tests {
         assert_eq!(target.len(), 64);
         assert_eq!(timeline.len(), 32);
     }
+
+    #[test]
+    fn annul() {
+        let mut target = 0;
+        let mut timeline = Timeline::<_, _, 32>::new();
+        timeline.apply(&mut target, Step(2)).unwrap();
+        timeline.apply(&mut target, Step(-2)).unwrap();
+        assert_eq!(target, 0);
+        assert_eq!(timeline.len(), 0);
+        assert!(!timeline.can_undo());
+        timeline.apply(&mut target, Step(3)).unwrap();
+        timeline.undo(&mut target).unwrap().unwrap();
+        assert_eq!(target, 0);
+    }
 }